@@ -0,0 +1,212 @@
+// Copyright 2016 Alex Crawford
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use serde::Deserialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A user-editable list of links that are allowed to fail validation,
+/// loaded from a `marker.toml` discovered at the documentation root or
+/// pointed at explicitly with `--config`.
+#[derive(Debug, Default, Deserialize)]
+pub struct Config {
+    #[serde(default, rename = "exception")]
+    exceptions: Vec<Exception>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Exception {
+    /// Exact link target to allow.
+    url: Option<String>,
+
+    /// Glob pattern matched against the link target.
+    target: Option<String>,
+
+    /// Glob pattern matched against the file containing the link; on its
+    /// own, suppresses every link found in matching files.
+    file: Option<String>,
+
+    /// Report the match instead of silently suppressing it.
+    #[serde(default)]
+    warn: bool,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Toml(toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref err) => write!(f, "{}", err),
+            ConfigError::Toml(ref err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, ConfigError> {
+        let contents = fs::read_to_string(path).map_err(ConfigError::Io)?;
+        toml::from_str(&contents).map_err(ConfigError::Toml)
+    }
+
+    /// Checks a link against the exceptions list. Returns `None` if the
+    /// link should be reported as usual, `Some(true)` if a matching
+    /// exception asked to still report it as a warning, or `Some(false)`
+    /// if it should be suppressed entirely.
+    pub fn should_suppress(&self, target: &str, file: &Path) -> Option<bool> {
+        self.exceptions
+            .iter()
+            .find(|exception| exception.matches(target, file))
+            .map(|exception| exception.warn)
+    }
+}
+
+impl Exception {
+    fn matches(&self, target: &str, file: &Path) -> bool {
+        if let Some(ref url) = self.url {
+            if target != url {
+                return false;
+            }
+        }
+
+        if let Some(ref pattern) = self.target {
+            if !glob_matches(pattern, target) {
+                return false;
+            }
+        }
+
+        if let Some(ref pattern) = self.file {
+            if !glob_matches_path(pattern, file) {
+                return false;
+            }
+        }
+
+        self.url.is_some() || self.target.is_some() || self.file.is_some()
+    }
+}
+
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    glob::Pattern::new(pattern)
+        .map(|pattern| pattern.matches(value))
+        .unwrap_or(false)
+}
+
+fn glob_matches_path(pattern: &str, path: &Path) -> bool {
+    glob::Pattern::new(pattern)
+        .map(|pattern| pattern.matches_path(path))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config_with(exception: Exception) -> Config {
+        Config {
+            exceptions: vec![exception],
+        }
+    }
+
+    #[test]
+    fn url_exception_matches_exactly() {
+        let config = config_with(Exception {
+            url: Some("https://example.com/paywalled".to_string()),
+            target: None,
+            file: None,
+            warn: false,
+        });
+
+        assert_eq!(
+            config.should_suppress("https://example.com/paywalled", Path::new("docs/a.md")),
+            Some(false)
+        );
+        assert_eq!(
+            config.should_suppress("https://example.com/other", Path::new("docs/a.md")),
+            None
+        );
+    }
+
+    #[test]
+    fn target_glob_exception_matches() {
+        let config = config_with(Exception {
+            url: None,
+            target: Some("https://example.com/*".to_string()),
+            file: None,
+            warn: false,
+        });
+
+        assert_eq!(
+            config.should_suppress("https://example.com/anything", Path::new("docs/a.md")),
+            Some(false)
+        );
+        assert_eq!(
+            config.should_suppress("https://other.com/anything", Path::new("docs/a.md")),
+            None
+        );
+    }
+
+    #[test]
+    fn file_glob_exception_matches() {
+        let config = config_with(Exception {
+            url: None,
+            target: None,
+            file: Some("docs/legacy/**".to_string()),
+            warn: false,
+        });
+
+        assert_eq!(
+            config.should_suppress("broken-target", Path::new("docs/legacy/old.md")),
+            Some(false)
+        );
+        assert_eq!(
+            config.should_suppress("broken-target", Path::new("docs/current.md")),
+            None
+        );
+    }
+
+    #[test]
+    fn warn_downgrades_instead_of_suppressing() {
+        let config = config_with(Exception {
+            url: Some("https://example.com/flaky".to_string()),
+            target: None,
+            file: None,
+            warn: true,
+        });
+
+        assert_eq!(
+            config.should_suppress("https://example.com/flaky", Path::new("docs/a.md")),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn empty_exception_never_matches() {
+        let config = config_with(Exception {
+            url: None,
+            target: None,
+            file: None,
+            warn: false,
+        });
+
+        assert_eq!(
+            config.should_suppress("https://example.com/anything", Path::new("docs/a.md")),
+            None
+        );
+    }
+}
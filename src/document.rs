@@ -13,6 +13,7 @@
 // limitations under the License.
 
 use pulldown_cmark::{Event as ParserEvent, LinkType, OffsetIter, Options, Parser, Tag};
+use std::collections::{HashMap, VecDeque};
 use std::ops::Range;
 
 pub struct Document<'a> {
@@ -21,6 +22,9 @@ pub struct Document<'a> {
 
     code_block: bool,
     last_text: Option<String>,
+    heading_text: Option<String>,
+    seen_anchors: HashMap<String, u32>,
+    pending: VecDeque<LocatedEvent>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -33,6 +37,8 @@ pub struct LocatedEvent {
 pub enum Event {
     // Link { target: &'a str, text: &'a str },
     Link { target: String, text: String },
+    Image { target: String, text: String },
+    Anchor { id: String },
     Error(Error),
 }
 
@@ -40,6 +46,7 @@ pub enum Event {
 pub enum Error {
     // ReferenceBroken{ target: &'a str, text: &'a str },
     ReferenceBroken { target: String, text: String },
+    DuplicateAnchor { anchor: String, suffixed: String },
 }
 
 impl<'a> Document<'a> {
@@ -55,6 +62,39 @@ impl<'a> Document<'a> {
 
             code_block: false,
             last_text: None,
+            heading_text: None,
+            seen_anchors: HashMap::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Registers an anchor `id`, returning the `LocatedEvent` to emit now.
+    /// If `id` collides with an earlier anchor in the same document, a
+    /// `DuplicateAnchor` error is returned immediately and the actual
+    /// (disambiguated) `Anchor` event is queued to follow it, so a later
+    /// `#id-1`-style fragment link still resolves.
+    fn register_anchor_event(&mut self, id: String, position: Range<usize>) -> LocatedEvent {
+        let count = self.seen_anchors.entry(id.clone()).or_insert(0);
+        let n = *count;
+        *count += 1;
+
+        if n == 0 {
+            self.new_located_event(Event::Anchor { id }, position)
+        } else {
+            let suffixed = format!("{}-{}", id, n);
+            self.pending.push_back(self.new_located_event(
+                Event::Anchor {
+                    id: suffixed.clone(),
+                },
+                position.clone(),
+            ));
+            self.new_located_event(
+                Event::Error(Error::DuplicateAnchor {
+                    anchor: id,
+                    suffixed,
+                }),
+                position,
+            )
         }
     }
 
@@ -75,10 +115,34 @@ impl<'a> Iterator for Document<'a> {
     type Item = LocatedEvent;
 
     fn next(&mut self) -> Option<LocatedEvent> {
+        if let Some(event) = self.pending.pop_front() {
+            return Some(event);
+        }
+
         while let Some((event, position)) = self.parser.next() {
             match event {
                 ParserEvent::Text(ref text) if !self.code_block => {
                     self.last_text = Some(text.to_string());
+                    if let Some(heading_text) = self.heading_text.as_mut() {
+                        heading_text.push_str(text);
+                    }
+                }
+                ParserEvent::Code(ref text) => {
+                    if let Some(heading_text) = self.heading_text.as_mut() {
+                        heading_text.push_str(text);
+                    }
+                }
+                ParserEvent::Html(ref html) => {
+                    if let Some(id) = extract_html_id(html) {
+                        return Some(self.register_anchor_event(id, position));
+                    }
+                }
+                ParserEvent::Start(Tag::Heading(_)) => self.heading_text = Some(String::new()),
+                ParserEvent::End(Tag::Heading(_)) => {
+                    let heading_text = self.heading_text.take().unwrap_or_default();
+                    let id = extract_custom_id(&heading_text)
+                        .unwrap_or_else(|| slugify(&heading_text));
+                    return Some(self.register_anchor_event(id, position));
                 }
                 ParserEvent::End(Tag::Link(link_type, target, text)) => match link_type {
                     LinkType::Inline
@@ -93,6 +157,27 @@ impl<'a> Iterator for Document<'a> {
                             position,
                         ))
                     }
+                    LinkType::Autolink => {
+                        return Some(self.new_located_event(
+                            Event::Link {
+                                target: target.to_string(),
+                                text: target.to_string(),
+                            },
+                            position,
+                        ))
+                    }
+                    LinkType::Email => {
+                        // The parser gives the bare address; add the scheme so
+                        // the link is checked as a `mailto:` URL.
+                        let target = format!("mailto:{}", target);
+                        return Some(self.new_located_event(
+                            Event::Link {
+                                text: target.clone(),
+                                target,
+                            },
+                            position,
+                        ));
+                    }
                     LinkType::ReferenceUnknown
                     | LinkType::CollapsedUnknown
                     | LinkType::ShortcutUnknown => {
@@ -104,8 +189,17 @@ impl<'a> Iterator for Document<'a> {
                             position,
                         ))
                     }
-                    _ => {}
                 },
+                ParserEvent::End(Tag::Image(_, target, _)) => {
+                    let text = self.last_text.clone().unwrap_or_default();
+                    return Some(self.new_located_event(
+                        Event::Image {
+                            target: target.to_string(),
+                            text,
+                        },
+                        position,
+                    ));
+                }
                 ParserEvent::Start(Tag::CodeBlock(_)) => self.code_block = true,
                 ParserEvent::End(Tag::CodeBlock(_)) => self.code_block = false,
                 _ => {}
@@ -116,6 +210,47 @@ impl<'a> Iterator for Document<'a> {
     }
 }
 
+/// Computes a GitHub-style heading slug: lowercase the text, drop every
+/// character that is not alphanumeric, a space, or a hyphen, then collapse
+/// runs of spaces into single hyphens.
+fn slugify(text: &str) -> String {
+    text.to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+        .collect::<String>()
+        .split(' ')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Pulls an explicit `{#custom-id}` suffix off a heading, as supported by
+/// several Markdown renderers even though GitHub itself ignores it.
+fn extract_custom_id(text: &str) -> Option<String> {
+    let trimmed = text.trim_end();
+    let trimmed = trimmed.strip_suffix('}')?;
+    let start = trimmed.rfind("{#")?;
+    let id = &trimmed[start + 2..];
+    if id.is_empty() {
+        None
+    } else {
+        Some(id.to_string())
+    }
+}
+
+/// Pulls an `id="..."` or `name="..."` attribute out of a raw HTML fragment,
+/// e.g. `<a name="install">` or `<div id="install">`.
+fn extract_html_id(html: &str) -> Option<String> {
+    extract_html_attr(html, "id").or_else(|| extract_html_attr(html, "name"))
+}
+
+fn extract_html_attr(html: &str, attr: &str) -> Option<String> {
+    let needle = format!("{}=\"", attr);
+    let start = html.find(&needle)? + needle.len();
+    let end = html[start..].find('"')?;
+    Some(html[start..start + end].to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -215,4 +350,157 @@ mod tests {
         let mut doc = Document::new("- [ ] Item 1\n- [ ] Item 2");
         assert_eq!(doc.next(), None);
     }
+
+    #[test]
+    fn heading_anchor() {
+        let mut doc = Document::new("# Getting Started!");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Anchor {
+                    id: "getting-started".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn heading_anchor_custom_id() {
+        let mut doc = Document::new("# Getting Started {#setup}");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Anchor {
+                    id: "setup".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn autolink() {
+        let mut doc = Document::new("<https://example.com/missing>");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Link {
+                    target: "https://example.com/missing".to_string(),
+                    text: "https://example.com/missing".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn email_autolink() {
+        let mut doc = Document::new("<jane@example.com>");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Link {
+                    target: "mailto:jane@example.com".to_string(),
+                    text: "mailto:jane@example.com".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn image() {
+        let mut doc = Document::new("![diagram](img/arch.png)");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Image {
+                    target: "img/arch.png".to_string(),
+                    text: "diagram".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn image_nested_in_link() {
+        let mut doc = Document::new("[![Badge](badge.png)](https://example.com)");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Image {
+                    target: "badge.png".to_string(),
+                    text: "Badge".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Link {
+                    target: "https://example.com".to_string(),
+                    text: "Badge".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn duplicate_heading_anchor() {
+        let mut doc = Document::new("# Setup\n\n# Setup");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Anchor {
+                    id: "setup".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Error(Error::DuplicateAnchor {
+                    anchor: "setup".to_string(),
+                    suffixed: "setup-1".to_string(),
+                }),
+                line: 3,
+            })
+        );
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Anchor {
+                    id: "setup-1".to_string(),
+                },
+                line: 3,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
+
+    #[test]
+    fn html_anchor() {
+        let mut doc = Document::new("<a name=\"install\"></a>\n\nText");
+        assert_eq!(
+            doc.next(),
+            Some(LocatedEvent {
+                event: Event::Anchor {
+                    id: "install".to_string(),
+                },
+                line: 1,
+            })
+        );
+        assert_eq!(doc.next(), None);
+    }
 }
@@ -16,7 +16,7 @@ use hyper;
 use std::fmt;
 use std::path::PathBuf;
 use std::sync::Arc;
-use url::ParseError;
+use url::{ParseError, Url};
 
 #[derive(Clone)]
 pub enum LinkError {
@@ -26,6 +26,10 @@ pub enum LinkError {
     HttpError(Arc<hyper::error::Error>),
     UrlMalformed(ParseError),
     ReferenceBroken,
+    FragmentMissing(String),
+    Redirected { to: Url },
+    TooManyRedirects,
+    EmailMalformed,
 }
 
 pub enum DocumentError {
@@ -33,6 +37,11 @@ pub enum DocumentError {
         text: String,
         target: String,
         error: LinkError,
+        image: bool,
+    },
+    DuplicateAnchor {
+        anchor: String,
+        suffixed: String,
     },
 }
 
@@ -59,6 +68,7 @@ impl fmt::Display for LocatedDocumentError {
                 ref text,
                 ref target,
                 ref error,
+                image,
             } => {
                 let (title, detail): (&str, Option<&dyn fmt::Display>) = match *error {
                     LinkError::PathAbsolute => ("Found absolute path", None),
@@ -67,6 +77,17 @@ impl fmt::Display for LocatedDocumentError {
                     LinkError::HttpError(ref err) => ("HTTP failure", Some(err)),
                     LinkError::UrlMalformed(ref err) => ("Found malformed URL", Some(err)),
                     LinkError::ReferenceBroken => ("Found broken reference", None),
+                    LinkError::FragmentMissing(ref fragment) => {
+                        ("Found missing anchor", Some(fragment))
+                    }
+                    LinkError::Redirected { ref to } => ("Found redirect", Some(to)),
+                    LinkError::TooManyRedirects => ("Found too many redirects", None),
+                    LinkError::EmailMalformed => ("Found malformed email", None),
+                };
+                let title = if image {
+                    format!("{} (image)", title)
+                } else {
+                    title.to_string()
                 };
                 match detail {
                     Some(detail) => write!(
@@ -81,6 +102,14 @@ impl fmt::Display for LocatedDocumentError {
                     ),
                 }
             }
+            DocumentError::DuplicateAnchor {
+                ref anchor,
+                ref suffixed,
+            } => write!(
+                formatter,
+                "{:22} ({} -> {}) at {}",
+                "Found duplicate anchor", anchor, suffixed, self.location
+            ),
         }
     }
 }
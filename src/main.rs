@@ -12,17 +12,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+mod config;
 mod document;
 mod error;
 
 use clap::Parser;
+use config::Config;
 use document::{Document, Error, Event};
 use error::{DocumentError, DocumentLocation, LinkError, LocatedDocumentError};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use std::process::exit;
 use url::{ParseError, Url};
 use walkdir::WalkDir;
@@ -43,11 +45,24 @@ macro_rules! printerror {
     }};
 }
 
+macro_rules! report_link_error {
+    ($config:expr, $link:expr, $error:expr, $flag:expr) => {{
+        let link = $link;
+        let error = $error;
+        match $config.should_suppress(&link.target, &link.file) {
+            Some(true) => println!("{}", link.new_error(error)),
+            Some(false) => {}
+            None => printerror!(link.new_error(error), $flag),
+        }
+    }};
+}
+
 struct LinkContext {
     target: String,
     text: String,
     line: usize,
     file: PathBuf,
+    image: bool,
 }
 
 impl LinkContext {
@@ -61,6 +76,7 @@ impl LinkContext {
                 text: self.text,
                 target: self.target,
                 error,
+                image: self.image,
             },
         }
     }
@@ -83,12 +99,46 @@ struct Options {
     /// Allow absolute path to join with root and evaluate
     #[clap(short, long)]
     allow_absolute_paths: bool,
+
+    /// Report URLs that redirect instead of silently following them
+    #[clap(long)]
+    report_redirects: bool,
+
+    /// Treat URLs that redirect as errors
+    #[clap(long)]
+    deny_redirects: bool,
+
+    /// Path to a marker.toml file listing link exceptions (defaults to
+    /// `<root>/marker.toml` if present)
+    #[clap(short, long)]
+    config: Option<PathBuf>,
 }
 
 fn main() {
     let options = Options::parse();
 
+    let config = match &options.config {
+        Some(path) => Config::load(path).unwrap_or_else(|error| {
+            fail!("Failed to load config ({}): {}", path.display(), error)
+        }),
+        None => {
+            let default_path = options.root.join("marker.toml");
+            if default_path.exists() {
+                Config::load(&default_path).unwrap_or_else(|error| {
+                    fail!(
+                        "Failed to load config ({}): {}",
+                        default_path.display(),
+                        error
+                    )
+                })
+            } else {
+                Config::default()
+            }
+        }
+    };
+
     let mut links = Vec::new();
+    let mut anchors: HashMap<PathBuf, HashSet<String>> = HashMap::new();
     let mut found_error = false;
 
     'entries: for entry in WalkDir::new(&options.root).into_iter().filter_map(|entry| {
@@ -131,16 +181,42 @@ fn main() {
                     target,
                     text,
                     line: event.line,
-                    file: entry.path().to_path_buf(),
+                    file: normalize_path(entry.path()),
+                    image: false,
+                }),
+                Event::Image { target, text } => links.push(LinkContext {
+                    target,
+                    text,
+                    line: event.line,
+                    file: normalize_path(entry.path()),
+                    image: true,
                 }),
-                Event::Error(Error::ReferenceBroken { target, text }) => printerror!(
+                Event::Anchor { id } => {
+                    anchors
+                        .entry(normalize_path(entry.path()))
+                        .or_insert_with(HashSet::new)
+                        .insert(id);
+                }
+                Event::Error(Error::ReferenceBroken { target, text }) => report_link_error!(
+                    config,
                     LinkContext {
                         target,
                         text,
                         line: event.line,
-                        file: entry.path().to_path_buf(),
-                    }
-                    .new_error(LinkError::ReferenceBroken),
+                        file: normalize_path(entry.path()),
+                        image: false,
+                    },
+                    LinkError::ReferenceBroken,
+                    found_error
+                ),
+                Event::Error(Error::DuplicateAnchor { anchor, suffixed }) => printerror!(
+                    LocatedDocumentError {
+                        location: DocumentLocation {
+                            path: entry.path().to_path_buf(),
+                            line: event.line,
+                        },
+                        error: DocumentError::DuplicateAnchor { anchor, suffixed },
+                    },
                     found_error
                 ),
             }
@@ -150,28 +226,58 @@ fn main() {
     let mut urls = HashMap::new();
     for link in links {
         match Url::parse(&link.target) {
+            Ok(ref url) if url.scheme() == "mailto" => {
+                if let Err(error) = check_email(url.path()) {
+                    report_link_error!(config, link, error, found_error)
+                }
+            }
             Ok(_) if options.skip_http => {}
             Ok(mut url) => {
                 url.set_fragment(None);
                 urls.entry(url).or_insert_with(Vec::new).push(link)
             }
             Err(ParseError::RelativeUrlWithoutBase) => {
-                if let Err(error) = check_path(
+                let fragment = link.target.split_once('#').map(|(_, f)| f.to_string());
+                match check_path(
                     &options.root,
                     &link.target,
                     &link.file,
                     options.allow_absolute_paths,
                 ) {
-                    printerror!(link.new_error(error), found_error)
+                    Ok(resolved) => {
+                        if let Some(fragment) = fragment {
+                            if !fragment.is_empty()
+                                && resolved.extension() == Some(OsStr::new("md"))
+                                && !anchors
+                                    .get(&resolved)
+                                    .map_or(false, |known| known.contains(&fragment))
+                            {
+                                report_link_error!(
+                                    config,
+                                    link,
+                                    LinkError::FragmentMissing(fragment),
+                                    found_error
+                                )
+                            }
+                        }
+                    }
+                    Err(error) => report_link_error!(config, link, error, found_error),
                 }
             }
-            Err(error) => printerror!(link.new_error(LinkError::UrlMalformed(error)), found_error),
+            Err(error) => {
+                report_link_error!(config, link, LinkError::UrlMalformed(error), found_error)
+            }
         }
     }
 
     #[cfg(feature = "network")]
     {
-        found_error |= check_urls(urls);
+        found_error |= check_urls(
+            urls,
+            &config,
+            options.report_redirects,
+            options.deny_redirects,
+        );
     }
 
     if found_error {
@@ -180,9 +286,15 @@ fn main() {
 }
 
 #[cfg(feature = "network")]
-fn check_urls(urls: HashMap<Url, Vec<LinkContext>>) -> bool {
+fn check_urls(
+    urls: HashMap<Url, Vec<LinkContext>>,
+    config: &Config,
+    report_redirects: bool,
+    deny_redirects: bool,
+) -> bool {
     use rayon::prelude::*;
     use reqwest::blocking::Client;
+    use reqwest::redirect::Policy;
     use std::time::Duration;
 
     let mut found_error = false;
@@ -190,20 +302,41 @@ fn check_urls(urls: HashMap<Url, Vec<LinkContext>>) -> bool {
     let client = match Client::builder()
         .user_agent(format!("marker/{}", clap::crate_version!()))
         .timeout(Duration::from_secs(10))
+        .redirect(Policy::none())
         .build()
     {
         Ok(client) => client,
         Err(err) => fail!("Failed to create HTTP client: {}", err),
     };
 
-    for (result, links) in urls
+    for ((result, redirects), links) in urls
         .into_par_iter()
-        .map(|(url, links)| (check_url(&client, &url), links))
+        .map(|(url, links)| (check_url(&client, &url, deny_redirects), links))
         .collect::<Vec<_>>()
     {
-        if let Err(error) = result {
-            for link in links {
-                printerror!(link.new_error(error.clone()), found_error)
+        match result {
+            Ok(()) => {
+                if report_redirects {
+                    for to in &redirects {
+                        for link in &links {
+                            println!(
+                                "{:22} ({} -> {}) at {}",
+                                "Redirected",
+                                link.target,
+                                to,
+                                DocumentLocation {
+                                    path: link.file.clone(),
+                                    line: link.line,
+                                }
+                            );
+                        }
+                    }
+                }
+            }
+            Err(error) => {
+                for link in links {
+                    report_link_error!(config, link, error.clone(), found_error)
+                }
             }
         }
     }
@@ -211,27 +344,71 @@ fn check_urls(urls: HashMap<Url, Vec<LinkContext>>) -> bool {
     found_error
 }
 
+/// Caps the number of redirects `check_url` will follow for a single link,
+/// matching reqwest's old default so a redirect cycle can't hang the run.
+const MAX_REDIRECTS: usize = 10;
+
+/// Follows redirects manually (rather than relying on reqwest's default
+/// policy) so each hop can be reported or, with `deny_redirects`, rejected
+/// outright. Returns the chain of `Location` targets that were followed.
 #[cfg(feature = "network")]
-fn check_url(client: &reqwest::blocking::Client, url: &Url) -> Result<(), LinkError> {
+fn check_url(
+    client: &reqwest::blocking::Client,
+    url: &Url,
+    deny_redirects: bool,
+) -> (Result<(), LinkError>, Vec<Url>) {
     use reqwest::StatusCode;
     use std::sync::Arc;
 
     if url.scheme() != "http" && url.scheme() != "https" {
-        return Ok(());
+        return (Ok(()), Vec::new());
     }
 
-    match client.head(url.clone()).send().and_then(|resp| {
-        if resp.status() == StatusCode::METHOD_NOT_ALLOWED {
-            client.get(url.clone()).send()
-        } else {
-            Ok(resp)
+    let mut redirects = Vec::new();
+    let mut current = url.clone();
+
+    loop {
+        if redirects.len() >= MAX_REDIRECTS {
+            return (Err(LinkError::TooManyRedirects), redirects);
         }
-    }) {
-        Ok(resp) => match resp.status() {
-            StatusCode::OK => Ok(()),
-            status => Err(LinkError::HttpStatus(status)),
-        },
-        Err(err) => Err(LinkError::HttpError(Arc::new(err))),
+
+        let response = client.head(current.clone()).send().and_then(|resp| {
+            if resp.status() == StatusCode::METHOD_NOT_ALLOWED {
+                client.get(current.clone()).send()
+            } else {
+                Ok(resp)
+            }
+        });
+
+        let resp = match response {
+            Ok(resp) => resp,
+            Err(err) => return (Err(LinkError::HttpError(Arc::new(err))), redirects),
+        };
+
+        if resp.status().is_redirection() {
+            let location = resp
+                .headers()
+                .get(reqwest::header::LOCATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| current.join(value).ok());
+
+            match location {
+                Some(next) => {
+                    if deny_redirects {
+                        return (Err(LinkError::Redirected { to: next }), redirects);
+                    }
+                    redirects.push(next.clone());
+                    current = next;
+                    continue;
+                }
+                None => return (Err(LinkError::HttpStatus(resp.status())), redirects),
+            }
+        }
+
+        return match resp.status() {
+            StatusCode::OK => (Ok(()), redirects),
+            status => (Err(LinkError::HttpStatus(status)), redirects),
+        };
     }
 }
 
@@ -240,8 +417,9 @@ fn check_path(
     target: &str,
     file: &Path,
     allow_absolute_paths: bool,
-) -> Result<(), LinkError> {
-    let path = Path::new(OsStr::new(target.split('#').next().expect("string")));
+) -> Result<PathBuf, LinkError> {
+    let path_str = target.split('#').next().expect("string");
+    let path = Path::new(OsStr::new(path_str));
 
     if path.is_absolute() {
         if !allow_absolute_paths {
@@ -250,21 +428,58 @@ fn check_path(
 
         let mut path_comps = path.components();
         path_comps.next();
+        let resolved = root.join(path_comps.as_path());
 
-        if root.join(path_comps.as_path()).exists() {
-            return Ok(());
+        return if resolved.exists() {
+            Ok(normalize_path(&resolved))
         } else {
-            return Err(LinkError::PathNonExistant);
-        }
+            Err(LinkError::PathNonExistant)
+        };
     }
 
-    if !file
-        .parent()
-        .expect("non-root file path")
-        .join(path)
-        .exists()
-    {
+    let resolved = if path_str.is_empty() {
+        file.to_path_buf()
+    } else {
+        file.parent().expect("non-root file path").join(path)
+    };
+
+    if !resolved.exists() {
         Err(LinkError::PathNonExistant)
+    } else {
+        Ok(normalize_path(&resolved))
+    }
+}
+
+/// Lexically collapses `.` and `..` components (e.g. `docs/sub/../guide.md`
+/// becomes `docs/guide.md`) without touching the filesystem, so a path can
+/// be used as a stable map key regardless of how it was spelled in a link.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(_)) => {}
+                _ => normalized.push(component.as_os_str()),
+            },
+            component => normalized.push(component.as_os_str()),
+        }
+    }
+    normalized
+}
+
+/// Checks the basic shape of a `mailto:` address: a non-empty local part
+/// and a domain part containing at least one dot, with no whitespace.
+fn check_email(address: &str) -> Result<(), LinkError> {
+    let mut parts = address.splitn(2, '@');
+    let local = parts.next().unwrap_or("");
+    let domain = parts.next().unwrap_or("");
+
+    if local.is_empty() || domain.is_empty() || !domain.contains('.') || address.contains(' ') {
+        Err(LinkError::EmailMalformed)
     } else {
         Ok(())
     }